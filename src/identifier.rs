@@ -1,42 +1,171 @@
 use std::fmt;
 use std::ops::Deref;
 
+use caseless::default_case_fold_str;
+use unicode_normalization::UnicodeNormalization;
+use unicode_xid::UnicodeXID;
+
 /// An identifier is the name for a database object.
 /// Table names, column names, constraint names are identifiers.
 ///
-/// Allowed characters:
+/// Identifiers come in two flavours, mirroring the SQL standard:
+///
+/// * **Unquoted** identifiers (constructed with [`Identifier::new`]).
+///   Allowed characters:
+///
+///   * Any character for which `UnicodeXID::is_xid_start` returns true, or
+///     `_`, as the first character.
+///   * Any character for which `UnicodeXID::is_xid_continue` returns true
+///     (this includes combining marks) for the remaining characters.
 ///
-/// * `a-z`
-/// * `A-Z`
-/// * `0-9`
-/// * `_`
-/// * Space (allowed in SQL with quoted identifiers)
+///   Other rules:
 ///
-/// Other rules:
+///   * Identifiers must have a length of at least 1 and at most
+///     [`MAX_IDENTIFIER_LEN`] characters.
+///   * Identifiers cannot start with a number (0-9) or space.
+///   * Identifiers are case insensitive.
 ///
-/// * Identifiers must have a minimum length of 1.
-/// * Identifiers cannot start with a number (0-9) or space.
-/// * Identifiers are case insensitive.
+///   [`Identifier::new`] reports precisely which of these rules was broken
+///   via [`IdentifierError`].
 ///
-/// When stored and compared, identifiers must be folded into a canonical,
-/// lower-case representation. This process is known as normalization.
-#[derive(PartialEq, Eq, Clone)]
+///   When stored and compared, unquoted identifiers are folded into a
+///   canonical representation. This process is known as normalization, and
+///   consists of Unicode NFC normalization followed by full Unicode default
+///   case folding (so that, for example, `Stra\u{df}e` and `STRASSE`
+///   compare equal, since full case folding maps `\u{df}` to `ss`).
+///
+/// * **Quoted** (delimited) identifiers (constructed with
+///   [`Identifier::new_quoted`]), e.g. `"Hello World"` in SQL source. These
+///   allow a much broader set of characters, including spaces and
+///   punctuation, preserve the original case verbatim, and compare
+///   case-sensitively, matching SQL-standard semantics where `"Foo"` and
+///   `foo` are distinct identifiers but `Foo` and `foo` are the same.
+#[derive(Clone)]
 pub struct Identifier {
-    value: String
+    value: String,
+    quoted: bool
 }
 
 impl Identifier {
-    pub fn new(value: &str) -> Option<Identifier>
+    /// Constructs an unquoted identifier, rejecting reserved SQL keywords.
+    /// Quoting (see [`Identifier::new_quoted`]) is the standard SQL escape
+    /// hatch for using a keyword as a name.
+    pub fn new(value: &str) -> Result<Identifier, IdentifierError>
     {
-        match normalize(value) {
-            Some(s) => Some(Identifier {
-                value: s
-            }),
-            None => None
+        let s = normalize(value)?;
+
+        if is_reserved_keyword(&s) {
+            Err(IdentifierError::ReservedKeyword)
+        } else {
+            Ok(Identifier {
+                value: s,
+                quoted: false
+            })
         }
     }
+
+    /// Constructs a quoted (delimited) identifier from the text between a
+    /// pair of double quotes, as written in SQL source. A doubled quote
+    /// (`""`) is decoded to a single literal `"`, and the case of `value` is
+    /// preserved verbatim rather than folded. Unlike [`Identifier::new`],
+    /// reserved keywords are allowed, since quoting is how SQL lets a
+    /// keyword be used as a name.
+    pub fn new_quoted(value: &str) -> Option<Identifier>
+    {
+        normalize_quoted(value).map(|s| Identifier {
+            value: s,
+            quoted: true
+        })
+    }
+
+    /// Whether this identifier was constructed via [`Identifier::new_quoted`]
+    /// rather than [`Identifier::new`].
+    pub fn is_quoted(&self) -> bool {
+        self.quoted
+    }
+}
+
+/// The maximum length, in `char`s, of an identifier. It applies to unquoted
+/// identifiers after normalization and to quoted identifiers after `""`
+/// decoding.
+pub const MAX_IDENTIFIER_LEN: usize = 128;
+
+/// The reason an identifier was rejected by [`Identifier::new`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum IdentifierError {
+    /// The identifier is empty.
+    Empty,
+    /// The identifier is longer than [`MAX_IDENTIFIER_LEN`] characters.
+    TooLong { len: usize, max: usize },
+    /// The identifier's first character is not a valid start character
+    /// (for example a digit or a space).
+    StartsWithDigitOrSpace(char),
+    /// The identifier contains a character that is not a valid Unicode XID
+    /// continue character.
+    InvalidCharacter(char),
+    /// `value` is a reserved SQL keyword; quote it (see
+    /// [`Identifier::new_quoted`]) to use it as a name.
+    ReservedKeyword
 }
 
+impl fmt::Display for IdentifierError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            IdentifierError::Empty =>
+                write!(f, "identifier cannot be empty"),
+            IdentifierError::TooLong { len, max } =>
+                write!(f, "identifier is {} characters long, exceeding the maximum of {}", len, max),
+            IdentifierError::StartsWithDigitOrSpace(c) =>
+                write!(f, "identifier cannot start with {:?}", c),
+            IdentifierError::InvalidCharacter(c) =>
+                write!(f, "identifier contains invalid character {:?}", c),
+            IdentifierError::ReservedKeyword =>
+                write!(f, "reserved keyword; quote it to use it as a name")
+        }
+    }
+}
+
+/// SQL reserved keywords, which cannot be used as unquoted identifiers.
+/// Entries are already lower-cased; match against a lower-cased value.
+/// Exposed so that other crate-internal consumers (e.g. the parser, for
+/// autocomplete or "did you mean" diagnostics) can enumerate the set
+/// instead of only testing membership via [`is_reserved_keyword`].
+pub static RESERVED_KEYWORDS: &[&str] = &[
+    "add", "all", "alter", "and", "as", "asc", "begin", "between", "boolean",
+    "by", "case", "char", "check", "column", "commit", "constraint", "create",
+    "cross", "cursor", "database", "date", "declare", "default", "delete",
+    "desc", "distinct", "double", "drop", "else", "end", "exec", "execute",
+    "exists", "float", "foreign", "from", "full", "function", "grant",
+    "group", "having", "in", "index", "inner", "insert", "int", "integer",
+    "into", "is", "join", "key", "left", "like", "limit", "not", "null",
+    "offset", "on", "or", "order", "outer", "primary", "procedure",
+    "references", "revoke", "right", "rollback", "schema", "select", "set",
+    "table", "text", "then", "time", "timestamp", "to", "transaction",
+    "trigger", "union", "unique", "update", "values", "varchar", "view",
+    "when", "where", "with"
+];
+
+/// Returns whether `value` is a reserved SQL keyword (case-insensitively),
+/// and therefore cannot be used as an unquoted identifier.
+pub fn is_reserved_keyword(value: &str) -> bool {
+    let folded = value.to_lowercase();
+    RESERVED_KEYWORDS.contains(&folded.as_str())
+}
+
+impl PartialEq for Identifier {
+    fn eq(&self, other: &Identifier) -> bool {
+        // Unquoted identifiers are already folded to their canonical form in
+        // `new`, while quoted identifiers retain their original case in
+        // `value`, so comparing `value` alone gives the SQL-standard
+        // semantics for both: folded-insensitive for unquoted names, exact
+        // for quoted ones. The `quoted` flag itself is not part of an
+        // identifier's identity.
+        self.value == other.value
+    }
+}
+
+impl Eq for Identifier {}
+
 impl Deref for Identifier {
     type Target = str;
 
@@ -55,39 +184,60 @@ impl fmt::Debug for Identifier {
     }
 }
 
-fn normalize(value: &str) -> Option<String> {
-
-    fn is_valid(value: &str) -> bool {
-        if let Some(c) = value.chars().nth(0) {
-            // Test if the first character is not a digit or space
-            match c {
-                '0'...'9' | ' ' => false,
-                _ => {
-                    value.chars().all(|c| {
-                        match c {
-                            'a'...'z' | 'A'...'Z' | '0'...'9' | '_' | ' ' => true,
-                            _ => false
-                        }
-                    })
-                }
-            }
-        } else {
-            false
-        }
+fn normalize(value: &str) -> Result<String, IdentifierError> {
+    if value.is_empty() {
+        return Err(IdentifierError::Empty);
+    }
+
+    let mut chars = value.chars();
+
+    // The first character must be `_` or a valid XID start character
+    // (digits and space are explicitly disallowed here).
+    let first = chars.next().unwrap();
+    if first != '_' && !UnicodeXID::is_xid_start(first) {
+        return Err(IdentifierError::StartsWithDigitOrSpace(first));
     }
 
-    if is_valid(value) {
-        Some(value.chars().map(|c| {
-            c.to_ascii_lowercase()
-        }).collect())
-    } else {
-        None
+    if let Some(c) = chars.find(|&c| !UnicodeXID::is_xid_continue(c)) {
+        return Err(IdentifierError::InvalidCharacter(c));
     }
+
+    // Normalize to NFC, then apply full Unicode default case folding (not
+    // mere lower-casing) so that equivalent identifiers compare and hash
+    // identically regardless of how they were typed, including cases like
+    // `\u{df}` (sharp s) folding to `ss`.
+    let normalized: String = default_case_fold_str(&value.nfc().collect::<String>());
+
+    // The length limit is checked against the normalized form, since that is
+    // what is actually stored and compared; combining marks that compose
+    // away under NFC should not count against the limit.
+    let len = normalized.chars().count();
+    if len > MAX_IDENTIFIER_LEN {
+        return Err(IdentifierError::TooLong { len, max: MAX_IDENTIFIER_LEN });
+    }
+
+    Ok(normalized)
+}
+
+fn normalize_quoted(value: &str) -> Option<String> {
+    if value.is_empty() {
+        return None;
+    }
+
+    // The only transformation a quoted identifier undergoes is decoding the
+    // standard SQL escape for a literal double quote; case is preserved.
+    let decoded = value.replace("\"\"", "\"");
+
+    if decoded.chars().count() > MAX_IDENTIFIER_LEN {
+        return None;
+    }
+
+    Some(decoded)
 }
 
 #[cfg(test)]
 mod test {
-    use super::Identifier;
+    use super::{Identifier, IdentifierError, MAX_IDENTIFIER_LEN, RESERVED_KEYWORDS};
 
     #[test]
     fn test_identifier() {
@@ -96,15 +246,108 @@ mod test {
         }
 
         fn cmp_none(a: &'static str) -> bool {
-            Identifier::new(a).is_none()
+            Identifier::new(a).is_err()
         }
 
         assert!(cmp("AbCdEfG", "abcdefg"));
         assert!(cmp("a0123456789", "a0123456789"));
-        assert!(cmp("Hello World", "hello world"));
         assert!(cmp_none(""));
         assert!(cmp_none("1a"));
         assert!(cmp_none(" abc "));
+        assert!(cmp_none("Hello World"));
         assert!(cmp("_1a", "_1a"));
+
+        // Unicode XID start/continue characters are accepted.
+        assert!(cmp("caf\u{e9}", "caf\u{e9}"));
+        assert!(cmp("\u{6570}\u{636e}\u{5e93}", "\u{6570}\u{636e}\u{5e93}"));
+
+        // Full case folding, not mere lower-casing: German sharp s (\u{df})
+        // folds to "ss", so "Stra\u{df}e" and "STRASSE" denote the same name.
+        assert!(cmp("Stra\u{df}e", "strasse"));
+        assert!(cmp("STRASSE", "strasse"));
+
+        // Combining marks are valid in the continue position.
+        assert!(cmp("e\u{301}cole", "\u{e9}cole"));
+    }
+
+    #[test]
+    fn test_quoted_identifier() {
+        // Quoted identifiers preserve case and spaces verbatim.
+        let quoted = Identifier::new_quoted("Hello World").unwrap();
+        assert_eq!(&quoted as &str, "Hello World");
+
+        // A doubled quote decodes to a single literal quote.
+        let escaped = Identifier::new_quoted("a\"\"b").unwrap();
+        assert_eq!(&escaped as &str, "a\"b");
+
+        assert!(Identifier::new_quoted("").is_none());
+
+        // Quoted identifiers compare case-sensitively: "Foo" != foo.
+        let foo_quoted = Identifier::new_quoted("Foo").unwrap();
+        let foo_unquoted = Identifier::new("foo").unwrap();
+        assert!(foo_quoted != foo_unquoted);
+
+        // But an unquoted identifier is still case-insensitive: Foo == foo.
+        assert!(Identifier::new("Foo").unwrap() == foo_unquoted);
+
+        // A quoted identifier that happens to already be in folded form
+        // denotes the same name as its unquoted counterpart.
+        let foo_quoted_lower = Identifier::new_quoted("foo").unwrap();
+        assert!(foo_quoted_lower == foo_unquoted);
+    }
+
+    #[test]
+    fn test_reserved_keyword() {
+        assert_eq!(Identifier::new("select"), Err(IdentifierError::ReservedKeyword));
+        assert_eq!(Identifier::new("SeLeCt"), Err(IdentifierError::ReservedKeyword));
+        assert_eq!(Identifier::new("order"), Err(IdentifierError::ReservedKeyword));
+
+        // Quoting is the escape hatch: a keyword can be quoted into a name.
+        assert!(Identifier::new_quoted("select").is_some());
+
+        // Non-keywords are unaffected.
+        assert!(Identifier::new("selected").is_ok());
+
+        // The keyword set itself, not just the membership predicate, is
+        // exposed for reuse (e.g. by the parser).
+        assert!(RESERVED_KEYWORDS.contains(&"select"));
+        assert!(!RESERVED_KEYWORDS.contains(&"selected"));
+    }
+
+    #[test]
+    fn test_identifier_error() {
+        assert_eq!(Identifier::new(""), Err(IdentifierError::Empty));
+        assert_eq!(Identifier::new("1a"), Err(IdentifierError::StartsWithDigitOrSpace('1')));
+        assert_eq!(Identifier::new(" abc"), Err(IdentifierError::StartsWithDigitOrSpace(' ')));
+        assert_eq!(Identifier::new("a b"), Err(IdentifierError::InvalidCharacter(' ')));
+
+        let too_long: String = "a".repeat(MAX_IDENTIFIER_LEN + 1);
+        assert_eq!(
+            Identifier::new(&too_long),
+            Err(IdentifierError::TooLong { len: MAX_IDENTIFIER_LEN + 1, max: MAX_IDENTIFIER_LEN })
+        );
+
+        let exactly_max: String = "a".repeat(MAX_IDENTIFIER_LEN);
+        assert!(Identifier::new(&exactly_max).is_ok());
+
+        // The length limit is measured on the normalized form: a sequence of
+        // "e" + combining acute accent composes under NFC to a single "é"
+        // per pair, so it should not be rejected just because the raw input
+        // has more `char`s than the limit.
+        let combining: String = "e\u{301}".repeat(MAX_IDENTIFIER_LEN);
+        assert!(Identifier::new(&combining).is_ok());
+
+        // Quoted identifiers are bounded by the same limit.
+        let too_long_quoted: String = "a".repeat(MAX_IDENTIFIER_LEN + 1);
+        assert!(Identifier::new_quoted(&too_long_quoted).is_none());
+    }
+
+    #[test]
+    fn test_identifier_error_display() {
+        assert_eq!(format!("{}", IdentifierError::Empty), "identifier cannot be empty");
+        assert_eq!(
+            format!("{}", IdentifierError::ReservedKeyword),
+            "reserved keyword; quote it to use it as a name"
+        );
     }
 }